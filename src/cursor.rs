@@ -4,7 +4,14 @@ use piston::{
     GameEvent,
     KeyPress,
     KeyPressArgs,
+    KeyRelease,
+    KeyReleaseArgs,
     keyboard,
+    MousePress,
+    MousePressArgs,
+    MouseRelease,
+    MouseReleaseArgs,
+    mouse,
     Update,
     UpdateArgs,
 };
@@ -21,6 +28,18 @@ use {
 pub enum Cursor<'a, A, S> {
     /// Keeps track of whether a key was pressed.
     KeyPressedCursor(keyboard::Key),
+    /// Keeps track of a key that autorepeats while held down.
+    ///
+    /// Fields: key, initial delay, repeat interval, whether the key is
+    /// held, the time accumulator, and whether the initial delay has
+    /// elapsed.
+    RepeatKeyCursor(keyboard::Key, f64, f64, bool, f64, bool),
+    /// Keeps track of whether a key was released.
+    KeyReleasedCursor(keyboard::Key),
+    /// Keeps track of whether a mouse button was pressed.
+    MousePressedCursor(mouse::Button),
+    /// Keeps track of whether a mouse button was released.
+    MouseReleasedCursor(mouse::Button),
     /// Keeps track of an event where you have a state of an action.
     State(&'a A, S),
     /// Keeps track of `Success` <=> `Failure`.
@@ -33,33 +52,98 @@ pub enum Cursor<'a, A, S> {
     SequenceCursor(&'a Vec<Event<A>>, uint, Box<Cursor<'a, A, S>>),
     /// Keeps track of an event where sub events are repeated sequentially.
     WhileCursor(Box<Cursor<'a, A, S>>, &'a Vec<Event<A>>, uint, Box<Cursor<'a, A, S>>),
+    /// Keeps track of an event where a sequence of sub events is repeated
+    /// from the start each time it succeeds, for as long as a guard event
+    /// keeps succeeding.
+    RepeatSequenceCursor(Box<Cursor<'a, A, S>>, &'a Vec<Event<A>>, uint, Box<Cursor<'a, A, S>>),
     /// Keeps track of an event where all sub events must happen.
     WhenAllCursor(Vec<Option<Cursor<'a, A, S>>>),
+    /// Keeps track of an event where any sub event must happen.
+    WhenAnyCursor(Vec<Option<Cursor<'a, A, S>>>),
+    /// Keeps track of an external signal, succeeding once the
+    /// user-supplied matcher fires for the incoming event.
+    SignalCursor,
 }
 
 impl<'a, A: StartState<S>, S> Cursor<'a, A, S> {
     /// Updates the cursor that tracks an event.
     ///
     /// The action need to return status and remaining delta time.
+    /// `g` is a matcher for `SignalCursor`, used to recognize external
+    /// event conditions that are not among the built-in key/mouse/update
+    /// variants.
     /// Returns status and the remaining delta time.
     pub fn update(
         &mut self,
         e: &GameEvent,
-        f: |dt: f64, action: &'a A, state: &mut S| -> (Status, f64)
+        f: |dt: f64, action: &'a A, state: &mut S| -> (Status, f64),
+        g: |e: &GameEvent| -> bool
     ) -> (Status, f64) {
         match (e, self) {
-            (&KeyPress(KeyPressArgs { key: key_pressed }), &KeyPressedCursor(key)) 
+            (&KeyPress(KeyPressArgs { key: key_pressed }), &KeyPressedCursor(key))
             if key_pressed == key => {
                 // Key press is considered to happen instantly.
                 (Success, 0.0)
             },
+            (&KeyPress(KeyPressArgs { key: key_pressed }),
+             &RepeatKeyCursor(key, _, _, ref mut held, ref mut t, ref mut initial_delay_elapsed))
+            if key_pressed == key && !*held => {
+                // The initial press is considered to happen instantly.
+                *held = true;
+                *t = 0.0;
+                *initial_delay_elapsed = false;
+                (Success, 0.0)
+            },
+            (&KeyRelease(KeyReleaseArgs { key: key_released }),
+             &RepeatKeyCursor(key, _, _, ref mut held, ref mut t, ref mut initial_delay_elapsed))
+            if key_released == key => {
+                // Releasing the key stops the repeats.
+                *held = false;
+                *t = 0.0;
+                *initial_delay_elapsed = false;
+                (Running, 0.0)
+            },
+            (&KeyRelease(KeyReleaseArgs { key: key_released }), &KeyReleasedCursor(key))
+            if key_released == key => {
+                // Key release is considered to happen instantly.
+                (Success, 0.0)
+            },
+            (&MousePress(MousePressArgs { button: button_pressed }), &MousePressedCursor(button))
+            if button_pressed == button => {
+                // Mouse press is considered to happen instantly.
+                (Success, 0.0)
+            },
+            (&MouseRelease(MouseReleaseArgs { button: button_released }), &MouseReleasedCursor(button))
+            if button_released == button => {
+                // Mouse release is considered to happen instantly.
+                (Success, 0.0)
+            },
+            (&Update(UpdateArgs { dt }),
+             &RepeatKeyCursor(_, initial_delay, interval, held, ref mut t, ref mut initial_delay_elapsed))
+            if held => {
+                *t += dt;
+                if !*initial_delay_elapsed {
+                    if *t >= initial_delay {
+                        *t -= initial_delay;
+                        *initial_delay_elapsed = true;
+                        (Success, *t)
+                    } else {
+                        (Running, 0.0)
+                    }
+                } else if *t >= interval {
+                    *t -= interval;
+                    (Success, *t)
+                } else {
+                    (Running, 0.0)
+                }
+            },
             (&Update(UpdateArgs { dt }), &State(action, ref mut state)) => {
                 // Call the function that updates the state.
                 f(dt, action, state)
             },
             (_, &InvertCursor(ref mut cur)) => {
                 // Invert `Success` <=> `Failure`.
-                match cur.update(e, |dt, action, state| f(dt, action, state)) {
+                match cur.update(e, |dt, action, state| f(dt, action, state), |e| g(e)) {
                     (Running, dt) => (Running, dt),
                     (Failure, dt) => (Success, dt),
                     (Success, dt) => (Failure, dt),
@@ -82,7 +166,7 @@ impl<'a, A: StartState<S>, S> Cursor<'a, A, S> {
             )) => {
                 let mut remaining_e = *e;
                 while *i < seq.len() {
-                    match cursor.update(&remaining_e, |dt, action, state| f(dt, action, state)) { 
+                    match cursor.update(&remaining_e, |dt, action, state| f(dt, action, state), |e| g(e)) { 
                         (Success, x) => return (Success, x),
                         (Running, _) => { break },
                         (Failure, new_dt) => {
@@ -114,7 +198,7 @@ impl<'a, A: StartState<S>, S> Cursor<'a, A, S> {
                 let cur = cursor;
                 let mut remaining_e = *e;
                 while *i < seq.len() {
-                    match cur.update(&remaining_e, |dt, action, state| f(dt, action, state)) {
+                    match cur.update(&remaining_e, |dt, action, state| f(dt, action, state), |e| g(e)) {
                         (Failure, x) => return (Failure, x),
                         (Running, _) => { break },
                         (Success, new_dt) => {
@@ -151,14 +235,14 @@ impl<'a, A: StartState<S>, S> Cursor<'a, A, S> {
                 ref mut cursor
             )) => {
                 // If the event terminates, do not execute the loop.
-                match ev_cursor.update(e, |dt, action, state| f(dt, action, state)) {
+                match ev_cursor.update(e, |dt, action, state| f(dt, action, state), |e| g(e)) {
                     (Running, _) => {}
                     x => return x,
                 };
                 let cur = cursor;
                 let mut remaining_e = *e;
                 loop {
-                    match cur.update(&remaining_e, |dt, action, state| f(dt, action, state)) {
+                    match cur.update(&remaining_e, |dt, action, state| f(dt, action, state), |e| g(e)) {
                         (Failure, x) => return (Failure, x),
                         (Running, _) => { break },
                         (Success, new_dt) => {
@@ -180,6 +264,65 @@ impl<'a, A: StartState<S>, S> Cursor<'a, A, S> {
                 }
                 (Running, 0.0)
             },
+            (_, &RepeatSequenceCursor(
+                ref mut ev_cursor,
+                rep,
+                ref mut i,
+                ref mut cursor
+            )) => {
+                let cur = cursor;
+                let mut remaining_e = *e;
+                loop {
+                    // Check the guard before running, or continuing to run,
+                    // the body sequence.
+                    match ev_cursor.update(&remaining_e, |dt, action, state| f(dt, action, state), |e| g(e)) {
+                        (Running, dt) => return (Running, dt),
+                        (Failure, dt) => return (Success, dt),
+                        (Success, new_dt) => {
+                            remaining_e = match remaining_e {
+                                // Change update event with remaining delta time.
+                                Update(_) => Update(UpdateArgs { dt: new_dt }),
+                                x => x,
+                            }
+                        }
+                    };
+                    // Run the body sequence to completion (same logic as `SequenceCursor`).
+                    while *i < rep.len() {
+                        match cur.update(&remaining_e, |dt, action, state| f(dt, action, state), |e| g(e)) {
+                            (Failure, x) => return (Failure, x),
+                            (Running, _) => return (Running, 0.0),
+                            (Success, new_dt) => {
+                                remaining_e = match remaining_e {
+                                    Update(_) => Update(UpdateArgs { dt: new_dt }),
+                                    // Other events are 'consumed' and not passed on.
+                                    _ => if *i == rep.len() - 1 {
+                                            // The sequence finished on a non-`Update`
+                                            // event; reset, but wait for the next tick
+                                            // to re-check the guard so the body is not
+                                            // advanced twice in one `update` call.
+                                            *i = 0;
+                                            **cur = rep[0].to_cursor();
+                                            return (Running, 0.0)
+                                        } else {
+                                            return (Running, 0.0)
+                                        }
+                                }
+                            }
+                        };
+                        *i += 1;
+                        if *i >= rep.len() {
+                            // Sequence finished with `dt` left over from an
+                            // `Update` event; reset and re-check the guard.
+                            *i = 0;
+                            **cur = rep[0].to_cursor();
+                            break;
+                        }
+                        // Create a new cursor for next event.
+                        // Use the same pointer to avoid allocation.
+                        **cur = rep[*i].to_cursor();
+                    }
+                }
+            },
             (_, &WhenAllCursor(ref mut cursors)) => {
                 // Get the least delta time left over.
                 let mut min_dt = std::f64::MAX_VALUE;
@@ -191,7 +334,8 @@ impl<'a, A: StartState<S>, S> Cursor<'a, A, S> {
                         Some(ref mut cur) => {
                             match cur.update(
                                 e,
-                                |dt, action, state| f(dt, action, state)
+                                |dt, action, state| f(dt, action, state),
+                                |e| g(e)
                             ) {
                                 (Running, _) => {},
                                 (Failure, new_dt) => return (Failure, new_dt),
@@ -215,6 +359,49 @@ impl<'a, A: StartState<S>, S> Cursor<'a, A, S> {
                     _ => (Running, 0.0)
                 }
             },
+            (_, &WhenAnyCursor(ref mut cursors)) => {
+                // Get the largest delta time left over.
+                let mut max_dt = 0.0;
+                // Count number of terminated events.
+                let mut terminated = 0;
+                for cur in cursors.mut_iter() {
+                    let res = match *cur {
+                        None => { terminated += 1; continue },
+                        Some(ref mut cur) => cur.update(
+                            e,
+                            |dt, action, state| f(dt, action, state),
+                            |e| g(e)
+                        ),
+                    };
+                    match res {
+                        (Running, _) => {},
+                        (Success, new_dt) => return (Success, new_dt),
+                        (Failure, new_dt) => {
+                            max_dt = max_dt.max(new_dt);
+                            terminated += 1;
+                            // A finished sub-cursor is removed so it is
+                            // skipped on later ticks.
+                            *cur = None;
+                        }
+                    }
+                }
+                match terminated {
+                    // If there are no events, succeed instantly.
+                    0 if cursors.len() == 0 => (Success, match *e {
+                            Update(UpdateArgs { dt }) => dt,
+                            // Other kind of events happen instantly.
+                            _ => 0.0
+                        }),
+                    // If all events terminated without a success,
+                    // the largest delta time is left.
+                    n if cursors.len() == n => (Failure, max_dt),
+                    _ => (Running, 0.0)
+                }
+            },
+            (_, &SignalCursor) if g(e) => {
+                // The external matcher fired; this is considered instant.
+                (Success, 0.0)
+            },
             _ => (Running, 0.0)
         }
     }
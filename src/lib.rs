@@ -0,0 +1,123 @@
+
+//! A behavior tree library for Rust.
+
+extern crate piston;
+
+use piston::{ keyboard, mouse };
+
+pub use cursor::Cursor;
+
+mod cursor;
+
+/// The result of a tick of a behavior.
+pub enum Status {
+    /// The behavior is still running, and needs more ticks to resolve.
+    Running,
+    /// The behavior resolved successfully.
+    Success,
+    /// The behavior resolved, but failed.
+    Failure,
+}
+
+/// Used to build a starting state from an action.
+pub trait StartState<S> {
+    /// Creates a start state.
+    fn start_state(&self) -> S;
+}
+
+/// Describes a behavior as a tree of sub events, which can be turned
+/// into a `Cursor` for tracking progress as game events arrive.
+pub enum Event<A> {
+    /// A leaf event backed by an external action.
+    Action(A),
+    /// Waits an amount of time before succeeding.
+    Wait(f64),
+    /// Converts `Success` into `Failure` and `Failure` into `Success`.
+    Invert(Box<Event<A>>),
+    /// Waits for a key to be pressed.
+    Press(keyboard::Key),
+    /// Waits for a key to be pressed, then succeeds again every `interval`
+    /// seconds after `initial_delay` for as long as the key is held.
+    PressedRepeat(keyboard::Key, f64, f64),
+    /// Waits for a key to be released.
+    Released(keyboard::Key),
+    /// Waits for a mouse button to be pressed.
+    MousePress(mouse::Button),
+    /// Waits for a mouse button to be released.
+    MouseRelease(mouse::Button),
+    /// Waits for an external, user-supplied condition on the current
+    /// game event to become true, via the matcher passed to `Cursor::update`.
+    Signal,
+    /// Runs sub events in sequence until one succeeds,
+    /// and fails if all sub events fail.
+    Select(Vec<Event<A>>),
+    /// Runs sub events in sequence until one fails,
+    /// and succeeds if all sub events succeed.
+    Sequence(Vec<Event<A>>),
+    /// Runs a sequence of sub events repeatedly while a guard event is
+    /// running, and resolves with the guard event's status once it terminates.
+    While(Box<Event<A>>, Vec<Event<A>>),
+    /// Runs a sequence of sub events repeatedly while a guard event
+    /// succeeds, restarting the sequence from the beginning each time
+    /// it completes successfully.
+    RepeatSequence(Box<Event<A>>, Vec<Event<A>>),
+    /// Runs all sub events in parallel and succeeds when all succeed,
+    /// failing as soon as one fails.
+    WhenAll(Vec<Event<A>>),
+    /// Runs all sub events in parallel and succeeds as soon as one succeeds,
+    /// failing when all fail.
+    WhenAny(Vec<Event<A>>),
+}
+
+impl<A> Event<A> {
+    /// Creates a cursor for tracking progress through the event tree.
+    pub fn to_cursor<'a, S>(&'a self) -> Cursor<'a, A, S>
+        where A: StartState<S>
+    {
+        use cursor::{
+            InvertCursor,
+            KeyPressedCursor,
+            KeyReleasedCursor,
+            MousePressedCursor,
+            MouseReleasedCursor,
+            RepeatKeyCursor,
+            RepeatSequenceCursor,
+            SelectCursor,
+            SequenceCursor,
+            SignalCursor,
+            State,
+            WaitCursor,
+            WhenAllCursor,
+            WhenAnyCursor,
+            WhileCursor,
+        };
+
+        match *self {
+            Action(ref action) => State(action, action.start_state()),
+            Wait(dt) => WaitCursor(dt, 0.0),
+            Invert(ref ev) => InvertCursor(box ev.to_cursor()),
+            Press(key) => KeyPressedCursor(key),
+            PressedRepeat(key, initial_delay, interval) => RepeatKeyCursor(
+                key, initial_delay, interval, false, 0.0, false
+            ),
+            Released(key) => KeyReleasedCursor(key),
+            MousePress(button) => MousePressedCursor(button),
+            MouseRelease(button) => MouseReleasedCursor(button),
+            Signal => SignalCursor,
+            Select(ref seq) => SelectCursor(seq, 0, box seq[0].to_cursor()),
+            Sequence(ref seq) => SequenceCursor(seq, 0, box seq[0].to_cursor()),
+            While(ref ev, ref rep) => WhileCursor(
+                box ev.to_cursor(), rep, 0, box rep[0].to_cursor()
+            ),
+            RepeatSequence(ref ev, ref rep) => RepeatSequenceCursor(
+                box ev.to_cursor(), rep, 0, box rep[0].to_cursor()
+            ),
+            WhenAll(ref seq) => WhenAllCursor(
+                seq.iter().map(|ev| Some(ev.to_cursor())).collect()
+            ),
+            WhenAny(ref seq) => WhenAnyCursor(
+                seq.iter().map(|ev| Some(ev.to_cursor())).collect()
+            ),
+        }
+    }
+}